@@ -0,0 +1,162 @@
+use chain_impl_mockchain::header::HeaderId;
+
+use crate::intercom::FragmentOrigin;
+
+use chain_impl_mockchain::fragment::{Fragment, FragmentId};
+
+/// A single entry in the on-disk persistent fragment log: enough to replay
+/// a fragment into the pool on startup. It does not record whether the
+/// fragment was already replayed before; replay folds repeats by fragment id.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct PersistentFragmentLog {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub fragment: Fragment,
+}
+
+/// Where a pending fragment currently stands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FragmentStatus {
+    Pending,
+    Rejected { reason: String },
+    InABlock { date: String, block: HeaderId },
+}
+
+/// A record of a single fragment's passage through the pool, kept around
+/// after the fragment itself has been dropped so status queries keep
+/// working for a while.
+#[derive(Clone, Debug)]
+pub struct Log {
+    fragment_id: FragmentId,
+    origin: FragmentOrigin,
+    status: FragmentStatus,
+    last_updated_at: chrono::DateTime<chrono::Utc>,
+    last_validated_block: Option<HeaderId>,
+}
+
+impl Log {
+    pub fn fragment_id(&self) -> &FragmentId {
+        &self.fragment_id
+    }
+
+    pub fn origin(&self) -> FragmentOrigin {
+        self.origin
+    }
+
+    pub fn status(&self) -> &FragmentStatus {
+        &self.status
+    }
+
+    /// When `status` last changed, used to measure e.g. accept-to-in-block
+    /// latency once a fragment lands in a block.
+    pub fn last_updated_at(&self) -> &chrono::DateTime<chrono::Utc> {
+        &self.last_updated_at
+    }
+
+    /// The most recent block this fragment was successfully re-validated
+    /// against, if any. Lets `Pools::revalidate` skip re-running the
+    /// ledger check for a fragment it has already confirmed still applies
+    /// to the current tip.
+    pub fn last_validated_block(&self) -> Option<HeaderId> {
+        self.last_validated_block
+    }
+}
+
+/// Bounded record of fragments the pool has seen, independent of whether
+/// they are still sitting in a pool. When full, the entry inserted first is
+/// evicted; updating an entry does not change its place in that order.
+pub struct Logs {
+    max_entries: usize,
+    entries: std::collections::HashMap<FragmentId, Log>,
+    order: std::collections::VecDeque<FragmentId>,
+}
+
+impl Logs {
+    pub fn new(max_entries: usize) -> Self {
+        Logs {
+            max_entries,
+            entries: std::collections::HashMap::new(),
+            order: std::collections::VecDeque::new(),
+        }
+    }
+
+    pub fn insert_pending(&mut self, id: FragmentId, origin: FragmentOrigin) {
+        if !self.entries.contains_key(&id) {
+            if self.order.len() >= self.max_entries {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.entries.remove(&oldest);
+                }
+            }
+            self.order.push_back(id);
+        }
+        self.entries.insert(
+            id,
+            Log {
+                fragment_id: id,
+                origin,
+                status: FragmentStatus::Pending,
+                last_updated_at: chrono::Utc::now(),
+                last_validated_block: None,
+            },
+        );
+    }
+
+    pub fn set_status(&mut self, id: FragmentId, status: FragmentStatus) {
+        if let Some(log) = self.entries.get_mut(&id) {
+            log.status = status;
+            log.last_updated_at = chrono::Utc::now();
+        }
+    }
+
+    /// Records that `id` has been re-checked against `block` and still
+    /// applies, so a later `revalidate` pass for the same block can skip it.
+    pub fn mark_validated(&mut self, id: FragmentId, block: HeaderId) {
+        if let Some(log) = self.entries.get_mut(&id) {
+            log.last_validated_block = Some(block);
+        }
+    }
+
+    pub fn logs(&self) -> impl Iterator<Item = &Log> {
+        self.entries.values()
+    }
+
+    pub fn logs_by_ids(&self, ids: Vec<FragmentId>) -> Vec<(FragmentId, &Log)> {
+        ids.into_iter()
+            .filter_map(|id| self.entries.get(&id).map(|log| (id, log)))
+            .collect()
+    }
+}
+
+/// Keeps only the first occurrence of each id (by the given id function),
+/// preserving order. Used to fold fragment ids across the persistent log
+/// files replayed on startup: a fragment replayed by a previous restart
+/// gets re-logged into whatever hourly file is open at the time, so it can
+/// legitimately appear in more than one file within the replay window.
+pub fn dedup_by_first_seen_id<T, K: Eq + std::hash::Hash>(
+    items: Vec<T>,
+    id_of: impl Fn(&T) -> K,
+) -> Vec<T> {
+    let mut seen = std::collections::HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(id_of(item)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_by_first_seen_id_keeps_first_occurrence_in_order() {
+        let items = vec![1, 2, 1, 3, 2, 4];
+        let deduped = dedup_by_first_seen_id(items, |n| *n);
+        assert_eq!(deduped, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn dedup_by_first_seen_id_is_a_no_op_without_duplicates() {
+        let items = vec!["a", "b", "c"];
+        let deduped = dedup_by_first_seen_id(items.clone(), |s| *s);
+        assert_eq!(deduped, items);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod logs;
+pub mod pool;
+pub mod process;
+
+pub use logs::{FragmentStatus, Log, Logs, PersistentFragmentLog};
+pub use pool::Pools;
+pub use process::{FragmentEvent, Process};
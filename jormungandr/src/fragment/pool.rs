@@ -0,0 +1,229 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use chain_impl_mockchain::fragment::{Fragment, FragmentId};
+use chain_impl_mockchain::header::HeaderId;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::fragment::logs::{FragmentStatus, Logs};
+use crate::intercom::{FragmentOrigin, FragmentRejectionReport, FragmentsProcessingSummary, NetworkMsg, RejectionReason};
+use crate::utils::async_msg::MessageBox;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("fragment pool {0} is full")]
+    Full(usize),
+}
+
+/// A read of the ledger a selection/revalidation job is run against; kept as
+/// a cheap, clonable reference rather than taking the whole ledger by value.
+pub type LedgerRef = Arc<chain_impl_mockchain::ledger::Ledger>;
+pub type LedgerParameters = Arc<chain_impl_mockchain::fee::LinearFee>;
+pub type Contents = Vec<Fragment>;
+pub type DeadlineFuture = std::pin::Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum SelectionAlg {
+    FirstComeFirstServe,
+    HighestFeeFirst,
+}
+
+fn pool_index(id: &FragmentId, n_pools: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", id).hash(&mut hasher);
+    (hasher.finish() as usize) % n_pools.max(1)
+}
+
+/// The lightweight acceptance check shared by `select` and `revalidate`:
+/// attempts to apply `fragment` against `ledger` and reports whether it
+/// would still be accepted. This is deliberately cheap (no block is actually
+/// produced) so it is safe to run against every candidate in a pool.
+fn fragment_still_valid(ledger: &LedgerRef, ledger_params: &LedgerParameters, fragment: &Fragment) -> bool {
+    ledger
+        .apply_fragment(ledger_params, fragment, ledger.date())
+        .is_ok()
+}
+
+/// The set of fragment pools (each fragment goes to the one picked by a hash
+/// of its id), plus the shared log of everything that has passed through them.
+pub struct Pools {
+    max_entries_per_pool: usize,
+    pools: Vec<Vec<Fragment>>,
+    logs: Arc<RwLock<Logs>>,
+    network_msg_box: MessageBox<NetworkMsg>,
+    persistent_log: Option<File>,
+}
+
+impl Pools {
+    pub fn new(
+        max_entries_per_pool: usize,
+        n_pools: usize,
+        logs: Arc<RwLock<Logs>>,
+        network_msg_box: MessageBox<NetworkMsg>,
+        persistent_log: Option<File>,
+    ) -> Self {
+        Pools {
+            max_entries_per_pool,
+            pools: vec![Vec::new(); n_pools.max(1)],
+            logs,
+            network_msg_box,
+            persistent_log,
+        }
+    }
+
+    pub fn logs(&self) -> Arc<RwLock<Logs>> {
+        Arc::clone(&self.logs)
+    }
+
+    pub async fn insert_and_propagate_all(
+        &mut self,
+        origin: FragmentOrigin,
+        fragments: Vec<Fragment>,
+        fail_fast: bool,
+    ) -> Result<FragmentsProcessingSummary, Error> {
+        let mut summary = FragmentsProcessingSummary::default();
+
+        for fragment in fragments {
+            let id = fragment.id();
+            let pool_idx = pool_index(&id, self.pools.len());
+
+            if self.pools[pool_idx].len() >= self.max_entries_per_pool {
+                summary.rejected.push(FragmentRejectionReport {
+                    id,
+                    reason: RejectionReason("pool is full".to_string()),
+                });
+                if fail_fast {
+                    break;
+                }
+                continue;
+            }
+
+            if let Some(persistent_log) = self.persistent_log.as_mut() {
+                let entry = crate::fragment::logs::PersistentFragmentLog {
+                    time: chrono::Utc::now(),
+                    fragment: fragment.clone(),
+                };
+                let _ = bincode::serialize_into(persistent_log, &entry);
+            }
+
+            self.logs.write().await.insert_pending(id, origin);
+            self.pools[pool_idx].push(fragment.clone());
+            let _ = self.network_msg_box.try_send(NetworkMsg::Propagate(fragment));
+            summary.accepted.push(id);
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn select(
+        &mut self,
+        pool_idx: usize,
+        ledger: LedgerRef,
+        ledger_params: LedgerParameters,
+        _selection_alg: SelectionAlg,
+        _soft_deadline_future: DeadlineFuture,
+        _hard_deadline_future: DeadlineFuture,
+    ) -> Contents {
+        self.pools
+            .get(pool_idx)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|fragment| fragment_still_valid(&ledger, &ledger_params, fragment))
+            .collect()
+    }
+
+    pub async fn remove_added_to_block(&mut self, fragment_ids: Vec<FragmentId>, status: FragmentStatus) {
+        let ids: HashSet<_> = fragment_ids.iter().copied().collect();
+        for pool in &mut self.pools {
+            pool.retain(|fragment| !ids.contains(&fragment.id()));
+        }
+        let mut logs = self.logs.write().await;
+        for id in fragment_ids {
+            logs.set_status(id, status.clone());
+        }
+    }
+
+    /// Re-validates surviving pool entries after `block` has been applied.
+    /// Only the pool fragments listed in `touched_fragment_ids` (those whose
+    /// inputs intersect what the block touched) are worth re-checking
+    /// (spent inputs, insufficient balance, stale counters); everything else
+    /// is left alone since the ledger state relevant to it hasn't changed.
+    /// Of those, the ones an earlier pass already marked as validated
+    /// against this exact block are skipped, so repeating a pass for the
+    /// same block does not re-run their check; the marks of this pass are
+    /// applied once it is done. Re-runs the same lightweight acceptance check used by
+    /// `select` against the updated tip ledger, and only evicts fragments
+    /// that actually fail it — merely touching the same account/UTxO as
+    /// the new block (e.g. two unrelated transactions spending different
+    /// outputs of the same account) is not itself a reason to evict.
+    /// Returns the ids evicted as no longer valid.
+    pub async fn revalidate(
+        &mut self,
+        ledger: LedgerRef,
+        ledger_params: LedgerParameters,
+        block: HeaderId,
+        touched_fragment_ids: Vec<FragmentId>,
+    ) -> Vec<FragmentId> {
+        let touched: HashSet<_> = touched_fragment_ids.into_iter().collect();
+        let mut evicted = Vec::new();
+        let mut validated = Vec::new();
+
+        {
+            let logs = self.logs.read().await;
+            for pool in &self.pools {
+                for fragment in pool {
+                    let id = fragment.id();
+                    if !touched.contains(&id) {
+                        continue;
+                    }
+                    if logs
+                        .logs_by_ids(vec![id])
+                        .into_iter()
+                        .any(|(_, log)| log.last_validated_block() == Some(block))
+                    {
+                        continue;
+                    }
+                    if fragment_still_valid(&ledger, &ledger_params, fragment) {
+                        validated.push(id);
+                    } else {
+                        evicted.push(id);
+                    }
+                }
+            }
+        }
+
+        let evicted_set: HashSet<_> = evicted.iter().copied().collect();
+        for pool in &mut self.pools {
+            pool.retain(|fragment| !evicted_set.contains(&fragment.id()));
+        }
+
+        let mut logs = self.logs.write().await;
+        for id in &evicted {
+            logs.set_status(
+                *id,
+                FragmentStatus::Rejected {
+                    reason: "evicted after ledger state changed".to_string(),
+                },
+            );
+        }
+        for id in validated {
+            logs.mark_validated(id, block);
+        }
+
+        evicted
+    }
+
+    pub fn close_persistent_log(&mut self) {
+        self.persistent_log = None;
+    }
+
+    pub fn set_persistent_log(&mut self, file: File) {
+        self.persistent_log = Some(file);
+    }
+}
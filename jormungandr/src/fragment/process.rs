@@ -1,6 +1,6 @@
 use crate::{
-    fragment::{Logs, Pools},
-    intercom::{NetworkMsg, TransactionMsg},
+    fragment::{logs::dedup_by_first_seen_id, Logs, Pools},
+    intercom::{FragmentOrigin, FragmentsProcessingSummary, NetworkMsg, ReplyHandle, TransactionMsg},
     stats_counter::StatsCounter,
     utils::{
         async_msg::{MessageBox, MessageQueue},
@@ -8,22 +8,149 @@ use crate::{
     },
 };
 
+use chain_impl_mockchain::{
+    fragment::{Fragment, FragmentId},
+    header::HeaderId,
+};
+
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
 use chrono::{Duration, DurationRound, Utc};
 use futures::future;
 use thiserror::Error;
+use tokio::sync::{broadcast, Mutex, Semaphore};
 use tokio_stream::StreamExt;
 use tracing::{span, Level};
 use tracing_futures::Instrument;
 
+/// Number of events a subscriber may fall behind by without missing any;
+/// past that it misses the oldest ones, is notified via `RecvError::Lagged`
+/// and has to resync through `GetLogs`.
+const FRAGMENT_EVENTS_CAPACITY: usize = 1024;
+
+/// How many spawned `SelectTransactions` jobs may be in flight at once, and
+/// how many `SendTransactions` batches may wait in the queue of the
+/// sequential send worker. Bounding this keeps a burst of large jobs from
+/// starving the runtime, while cheap read-only queries proceed independently.
+const MAX_CONCURRENT_HEAVY_JOBS: usize = 4;
+const SEND_TRANSACTIONS_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+const SELECT_TRANSACTIONS_TIMEOUT: StdDuration = StdDuration::from_secs(5);
+
+/// A notable event in the life of a fragment as it moves through the pool.
+///
+/// Consumers obtain a stream of these via `TransactionMsg::Subscribe` to
+/// drive real-time status updates (e.g. REST/websocket handlers) instead of
+/// polling `GetStatuses`.
+#[derive(Clone, Debug)]
+pub enum FragmentEvent {
+    Received { id: FragmentId, origin: FragmentOrigin },
+    Accepted { id: FragmentId },
+    Rejected { id: FragmentId, reason: String },
+    InBlock { id: FragmentId, block: HeaderId },
+    Dropped { id: FragmentId },
+}
+
+/// Pending work item for the sequential `SendTransactions` worker (see
+/// `Process::start`): the pool lock is only ever held while processing one
+/// of these at a time, so a later batch can never apply before an earlier
+/// one it may depend on.
+type SendTransactionsJob = (FragmentOrigin, Vec<Fragment>, bool, ReplyHandle<FragmentsProcessingSummary>);
+
+/// Records per-fragment accept/reject events and the receive-to-accept
+/// latency for a completed `insert_and_propagate_all` job, and logs the
+/// error case. Returns the summary on success so the caller can reply with
+/// it.
+fn apply_send_transactions_result(
+    stats_counter: &StatsCounter,
+    fragment_events_sender: &broadcast::Sender<FragmentEvent>,
+    received_at: chrono::DateTime<Utc>,
+    result: Result<FragmentsProcessingSummary, crate::fragment::pool::Error>,
+) -> Option<FragmentsProcessingSummary> {
+    match result {
+        Ok(summary) => {
+            stats_counter.add_tx_recv_cnt(summary.accepted.len());
+
+            let accept_latency_us = (Utc::now() - received_at)
+                .num_microseconds()
+                .unwrap_or(0)
+                .max(0) as u64;
+            for id in &summary.accepted {
+                stats_counter.record_receive_to_accept_latency(accept_latency_us);
+                let _ = fragment_events_sender.send(FragmentEvent::Accepted { id: *id });
+            }
+            for rejected in &summary.rejected {
+                let _ = fragment_events_sender.send(FragmentEvent::Rejected {
+                    id: rejected.id,
+                    reason: rejected.reason.to_string(),
+                });
+            }
+            Some(summary)
+        }
+        Err(err) => {
+            tracing::error!("failed to insert fragments into the pool: {}", err);
+            None
+        }
+    }
+}
+
+/// Applies one `SendTransactions` batch to the pool and replies, racing the
+/// job against `SEND_TRANSACTIONS_TIMEOUT` without ever dropping it:
+/// `insert_and_propagate_all` holds the pool lock and is not known to be
+/// cancel-safe, so a stalled job still runs to completion in the
+/// background, keeping the pool and persistent log consistent, even after
+/// an early reply has already gone out.
+async fn process_send_transactions(
+    pool: &Arc<Mutex<Pools>>,
+    stats_counter: &StatsCounter,
+    fragment_events_sender: &broadcast::Sender<FragmentEvent>,
+    origin: FragmentOrigin,
+    fragments: Vec<Fragment>,
+    fail_fast: bool,
+    reply_handle: ReplyHandle<FragmentsProcessingSummary>,
+) {
+    let received_at = Utc::now();
+    for fragment in &fragments {
+        let _ = fragment_events_sender.send(FragmentEvent::Received {
+            id: fragment.id(),
+            origin,
+        });
+    }
+
+    let pool = Arc::clone(pool);
+    let job = async move {
+        let mut pool = pool.lock().await;
+        pool.insert_and_propagate_all(origin, fragments, fail_fast).await
+    };
+    tokio::pin!(job);
+
+    tokio::select! {
+        result = &mut job => {
+            let summary = apply_send_transactions_result(stats_counter, fragment_events_sender, received_at, result);
+            reply_handle.reply_ok(summary.unwrap_or_default());
+        }
+        _ = tokio::time::sleep(SEND_TRANSACTIONS_TIMEOUT) => {
+            tracing::warn!(
+                "SendTransactions stalled past {:?}; replying early and letting it finish in the background",
+                SEND_TRANSACTIONS_TIMEOUT
+            );
+            reply_handle.reply_ok(FragmentsProcessingSummary::default());
+            let result = job.await;
+            apply_send_transactions_result(stats_counter, fragment_events_sender, received_at, result);
+        }
+    }
+}
+
 pub struct Process {
     pool_max_entries: usize,
     logs_max_entries: usize,
     network_msg_box: MessageBox<NetworkMsg>,
+    fragment_events_sender: broadcast::Sender<FragmentEvent>,
+    persistent_log_max_replay_age: Option<Duration>,
 }
 
 #[derive(Debug, Error)]
@@ -39,11 +166,15 @@ impl Process {
         pool_max_entries: usize,
         logs_max_entries: usize,
         network_msg_box: MessageBox<NetworkMsg>,
+        persistent_log_max_replay_age: Option<Duration>,
     ) -> Self {
+        let (fragment_events_sender, _) = broadcast::channel(FRAGMENT_EVENTS_CAPACITY);
         Process {
             pool_max_entries,
             logs_max_entries,
             network_msg_box,
+            fragment_events_sender,
+            persistent_log_max_replay_age,
         }
     }
 
@@ -67,6 +198,79 @@ impl Process {
             }
         }
 
+        async fn replay_persistent_log(
+            dir: &Path,
+            max_age: Option<Duration>,
+        ) -> Result<Vec<chain_impl_mockchain::fragment::Fragment>, Error> {
+            let mut log_file_names: Vec<PathBuf> = match fs::read_dir(dir) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "log"))
+                    .collect(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(Error::PersistentLog(err)),
+            };
+            // Hourly file names (`%Y-%m-%d_%H.log`) sort lexicographically in
+            // timestamp order, so fragments are replayed in the order they
+            // were originally received.
+            log_file_names.sort();
+
+            let cutoff = max_age.map(|age| Utc::now() - age);
+            let mut replayed = Vec::new();
+
+            for path in log_file_names {
+                if let Some(cutoff) = cutoff {
+                    let file_time = path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .and_then(|stem| {
+                            chrono::NaiveDateTime::parse_from_str(stem, "%Y-%m-%d_%H").ok()
+                        })
+                        .map(|naive| chrono::DateTime::<Utc>::from_utc(naive, Utc));
+                    if matches!(file_time, Some(t) if t < cutoff) {
+                        tracing::debug!("ignoring stale persistent log `{:?}`", path);
+                        continue;
+                    }
+                }
+
+                let file = File::open(&path).map_err(Error::PersistentLog)?;
+                let mut reader = io::BufReader::new(file);
+                loop {
+                    match bincode::deserialize_from::<_, crate::fragment::PersistentFragmentLog>(
+                        &mut reader,
+                    ) {
+                        Ok(entry) => {
+                            replayed.push(entry.fragment);
+                        }
+                        Err(err) => {
+                            if let bincode::ErrorKind::Io(io_err) = &*err {
+                                if io_err.kind() == io::ErrorKind::UnexpectedEof {
+                                    break;
+                                }
+                            }
+                            tracing::warn!(
+                                "skipping trailing partial record in `{:?}`: {}",
+                                path,
+                                err
+                            );
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // A fragment replayed by a previous restart gets re-logged into
+            // whatever hourly file was open at the time, so the same id can
+            // legitimately turn up in more than one file within the window
+            // read above. Folding by id across everything just read (rather
+            // than comparing against the brand-new, still-empty pool) is
+            // what keeps an overlapping replay window idempotent.
+            let replayed = dedup_by_first_seen_id(replayed, |fragment| fragment.id());
+
+            Ok(replayed)
+        }
+
         fn open_log_file(dir: &Path) -> Result<File, Error> {
             let mut path: PathBuf = dir.into();
             if !path.exists() {
@@ -89,9 +293,18 @@ impl Process {
                 "Having 'log_max_entries' < 'pool_max_entries' * n_pools is not recommendend. Overriding 'log_max_entries' to {}", min_logs_size
             );
         }
-        let logs = Logs::new(std::cmp::max(self.logs_max_entries, min_logs_size));
+        let logs = Arc::new(tokio::sync::RwLock::new(Logs::new(std::cmp::max(
+            self.logs_max_entries,
+            min_logs_size,
+        ))));
 
         let mut wakeup = Box::pin(hourly_wakeup(persistent_log_dir.is_some()));
+        let fragment_events_sender = self.fragment_events_sender.clone();
+        // Kept alongside `pool` so read-only queries (`GetLogs`/`GetStatuses`)
+        // can take their own read lock on the logs directly, instead of
+        // going through the pool's mutex and waiting behind whatever heavy
+        // `SendTransactions`/`SelectTransactions` job is in flight.
+        let logs_handle = Arc::clone(&logs);
 
         async move {
             let persistent_log = match &persistent_log_dir {
@@ -110,6 +323,62 @@ impl Process {
                 persistent_log,
             );
 
+            if let Some(dir) = &persistent_log_dir {
+                let replayed =
+                    replay_persistent_log(dir.as_ref(), self.persistent_log_max_replay_age)
+                        .await?;
+                if !replayed.is_empty() {
+                    tracing::info!(
+                        "replaying {} pending fragment(s) from the persistent log",
+                        replayed.len()
+                    );
+                    pool.insert_and_propagate_all(FragmentOrigin::Replayed, replayed, false)
+                        .await?;
+                }
+            }
+
+            // Wrapped so that the heavy selection/insertion jobs below can run as
+            // spawned sub-tasks without blocking this loop from picking up the
+            // next message. `GetLogs`/`GetStatuses` never touch this mutex at
+            // all (see `logs_handle` above); `RemoveTransactions` and
+            // `MaintainPool` are spawned too so a slow mutation never stalls
+            // the select loop's ability to dequeue the next message.
+            let pool = Arc::new(Mutex::new(pool));
+            let heavy_job_limiter = Arc::new(Semaphore::new(MAX_CONCURRENT_HEAVY_JOBS));
+
+            // `SendTransactions` batches may depend on each other (a later
+            // fragment spending the output of an earlier one in the same or
+            // a prior batch), so they cannot be validated out of submission
+            // order. Racing independent spawns for the pool lock (as with
+            // `SelectTransactions`) would let a later batch apply first;
+            // instead every send is handed to a single sequential worker fed
+            // by a bounded channel, which preserves FIFO order while still
+            // letting the select loop immediately move on to the next
+            // message.
+            let (send_tx, mut send_rx) =
+                tokio::sync::mpsc::channel::<SendTransactionsJob>(MAX_CONCURRENT_HEAVY_JOBS);
+            tokio::spawn({
+                let pool = Arc::clone(&pool);
+                let stats_counter = stats_counter.clone();
+                let fragment_events_sender = fragment_events_sender.clone();
+                async move {
+                    while let Some((origin, fragments, fail_fast, reply_handle)) =
+                        send_rx.recv().await
+                    {
+                        process_send_transactions(
+                            &pool,
+                            &stats_counter,
+                            &fragment_events_sender,
+                            origin,
+                            fragments,
+                            fail_fast,
+                            reply_handle,
+                        )
+                        .await;
+                    }
+                }
+            });
+
             loop {
                 tokio::select! {
                     maybe_msg = input.next() => {
@@ -128,31 +397,98 @@ impl Process {
                                     // for other message we don't want to receive them through this interface, and possibly
                                     // put them in another pool.
 
-                                    let stats_counter = stats_counter.clone();
-
-                                    let summary = pool
-                            .insert_and_propagate_all(origin, fragments, fail_fast)
-                            .await?;
-
-                        stats_counter.add_tx_recv_cnt(summary.accepted.len());
-
-                        reply_handle.reply_ok(summary);
+                                    // Handed off to the sequential send worker (see above) so a
+                                    // large batch doesn't block this loop, while still applying
+                                    // batches in the order they were submitted.
+                                    if send_tx
+                                        .send((origin, fragments, fail_fast, reply_handle))
+                                        .await
+                                        .is_err()
+                                    {
+                                        tracing::error!("send transactions worker is gone");
+                                    }
                                 }
                                 TransactionMsg::RemoveTransactions(fragment_ids, status) => {
-                                    tracing::debug!(
-                                        "removing fragments added to block {:?}: {:?}",
-                                        status,
-                                        fragment_ids
-                                    );
-                                    pool.remove_added_to_block(fragment_ids, status);
+                                    // Spawned, like the heavy jobs above, so the mutation
+                                    // triggered by a newly applied block never stalls this
+                                    // loop's ability to dequeue the next message.
+                                    let pool = Arc::clone(&pool);
+                                    let stats_counter = stats_counter.clone();
+                                    let fragment_events_sender = fragment_events_sender.clone();
+                                    tokio::spawn(async move {
+                                        tracing::debug!(
+                                            "removing fragments added to block {:?}: {:?}",
+                                            status,
+                                            fragment_ids
+                                        );
+                                        let mut pool = pool.lock().await;
+                                        if let crate::fragment::FragmentStatus::InABlock { block, .. } = &status {
+                                            let now = Utc::now();
+                                            for (_, log) in pool.logs().read().await.logs_by_ids(fragment_ids.clone()) {
+                                                let in_block_latency_us = (now - *log.last_updated_at())
+                                                    .num_microseconds()
+                                                    .unwrap_or(0)
+                                                    .max(0) as u64;
+                                                stats_counter
+                                                    .record_accept_to_in_block_latency(in_block_latency_us);
+                                            }
+                                            for id in &fragment_ids {
+                                                let _ = fragment_events_sender.send(FragmentEvent::InBlock {
+                                                    id: *id,
+                                                    block: *block,
+                                                });
+                                            }
+                                        }
+                                        pool.remove_added_to_block(fragment_ids, status).await;
+                                    });
+                                }
+                                TransactionMsg::MaintainPool {
+                                    ledger,
+                                    ledger_params,
+                                    block,
+                                    touched_fragment_ids,
+                                } => {
+                                    // The ledger moved on when the block above was applied, so
+                                    // fragments still sitting in the pool that touch the same
+                                    // accounts/UTxOs may no longer be valid (spent inputs,
+                                    // insufficient balance, stale counters). Only entries that
+                                    // intersect what the new block touched are worth
+                                    // re-checking, and `revalidate` only evicts the ones that
+                                    // actually fail the ledger check, not every entry that
+                                    // merely touches the same account/UTxO. Spawned for the
+                                    // same reason as `RemoveTransactions` above.
+                                    let pool = Arc::clone(&pool);
+                                    let fragment_events_sender = fragment_events_sender.clone();
+                                    tokio::spawn(async move {
+                                        let evicted = pool
+                                            .lock()
+                                            .await
+                                            .revalidate(ledger, ledger_params, block, touched_fragment_ids)
+                                            .await;
+                                        for id in evicted {
+                                            let _ = fragment_events_sender.send(FragmentEvent::Dropped { id });
+                                        }
+                                    });
+                                }
+                                TransactionMsg::Subscribe(reply_handle) => {
+                                    // Broadcast channels drop messages for subscribers that fall
+                                    // behind; a `Lagged(n)` error on the returned receiver tells
+                                    // the consumer it needs to resync via `GetLogs`.
+                                    reply_handle.reply_ok(fragment_events_sender.subscribe());
+                                }
+                                TransactionMsg::GetLatencyStats(reply_handle) => {
+                                    reply_handle.reply_ok(stats_counter.fragment_latency_stats());
                                 }
                                 TransactionMsg::GetLogs(reply_handle) => {
-                                    let logs = pool.logs().logs().cloned().collect();
+                                    // Reads `logs_handle` directly rather than going through
+                                    // `pool`'s mutex, so this never waits behind a heavy
+                                    // `SendTransactions`/`SelectTransactions` job.
+                                    let logs = logs_handle.read().await.logs().cloned().collect();
                                     reply_handle.reply_ok(logs);
                                 }
                                 TransactionMsg::GetStatuses(fragment_ids, reply_handle) => {
                                     let mut statuses = HashMap::new();
-                                    pool.logs().logs_by_ids(fragment_ids).into_iter().for_each(
+                                    logs_handle.read().await.logs_by_ids(fragment_ids).into_iter().for_each(
                                         |(fragment_id, log)| {
                                             statuses.insert(fragment_id, log.status().clone());
                                         },
@@ -168,22 +504,57 @@ impl Process {
                                     soft_deadline_future,
                                     hard_deadline_future,
                                 } => {
-                                    let contents = pool
-                                        .select(
-                                            pool_idx,
-                                            ledger,
-                                            ledger_params,
-                                            selection_alg,
-                                            soft_deadline_future,
-                                            hard_deadline_future,
-                                        )
-                                        .await;
-                                    reply_handle.reply_ok(contents);
+                                    // `pool.select` does not observe its soft/hard deadline
+                                    // futures, so nothing bounds how long the validation
+                                    // itself can run; spawn it off the
+                                    // main loop so a runaway validation cannot wedge unrelated
+                                    // GetLogs/GetStatuses queries.
+                                    let pool = Arc::clone(&pool);
+                                    let limiter = Arc::clone(&heavy_job_limiter);
+
+                                    tokio::spawn(async move {
+                                        let _permit = match limiter.acquire_owned().await {
+                                            Ok(permit) => permit,
+                                            Err(_) => return,
+                                        };
+
+                                        let job = async move {
+                                            let mut pool = pool.lock().await;
+                                            pool.select(
+                                                pool_idx,
+                                                ledger,
+                                                ledger_params,
+                                                selection_alg,
+                                                soft_deadline_future,
+                                                hard_deadline_future,
+                                            )
+                                            .await
+                                        };
+                                        tokio::pin!(job);
+
+                                        // `pool.select` holds the pool lock and is not known to
+                                        // be cancel-safe, so it is never dropped mid-await: the
+                                        // timeout only bounds how long we *wait* for a reply,
+                                        // never the selection itself, which always runs to
+                                        // completion even if we've already given up on it.
+                                        tokio::select! {
+                                            contents = &mut job => reply_handle.reply_ok(contents),
+                                            _ = tokio::time::sleep(SELECT_TRANSACTIONS_TIMEOUT) => {
+                                                tracing::warn!(
+                                                    "SelectTransactions stalled past {:?}; replying with an empty selection and letting it finish in the background",
+                                                    SELECT_TRANSACTIONS_TIMEOUT
+                                                );
+                                                reply_handle.reply_ok(Default::default());
+                                                job.await;
+                                            }
+                                        }
+                                    });
                                 }
                             }
                         }
                     }
                     _ = &mut wakeup => {
+                        let mut pool = pool.lock().await;
                         pool.close_persistent_log();
                         let dir = persistent_log_dir.as_ref().unwrap();
                         let file = open_log_file(dir.as_ref())?;
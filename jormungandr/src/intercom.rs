@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use chain_impl_mockchain::fragment::{Fragment, FragmentId};
+use chain_impl_mockchain::header::HeaderId;
+use tokio::sync::{broadcast, oneshot};
+
+use crate::fragment::pool::{Contents, DeadlineFuture, LedgerParameters, LedgerRef, SelectionAlg};
+use crate::fragment::{FragmentEvent, FragmentStatus, Log};
+use crate::stats_counter::FragmentLatencyStats;
+
+/// Where a fragment came from; recorded in the fragment's `Log`. The pool
+/// propagates accepted fragments to the network whatever their origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentOrigin {
+    Api,
+    Network,
+    /// Re-inserted from the persistent fragment log at startup, rather than
+    /// freshly received from a peer or a client.
+    Replayed,
+}
+
+#[derive(Debug)]
+pub enum NetworkMsg {
+    Propagate(Fragment),
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectionReason(pub String);
+
+impl std::fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct FragmentRejectionReport {
+    pub id: FragmentId,
+    pub reason: RejectionReason,
+}
+
+#[derive(Debug, Default)]
+pub struct FragmentsProcessingSummary {
+    pub accepted: Vec<FragmentId>,
+    pub rejected: Vec<FragmentRejectionReport>,
+}
+
+/// A one-shot reply channel paired with a `TransactionMsg` request.
+pub struct ReplyHandle<T> {
+    sender: oneshot::Sender<T>,
+}
+
+impl<T> ReplyHandle<T> {
+    pub fn new() -> (Self, oneshot::Receiver<T>) {
+        let (sender, receiver) = oneshot::channel();
+        (ReplyHandle { sender }, receiver)
+    }
+
+    pub fn reply_ok(self, value: T) {
+        let _ = self.sender.send(value);
+    }
+}
+
+pub enum TransactionMsg {
+    SendTransactions {
+        origin: FragmentOrigin,
+        fragments: Vec<Fragment>,
+        fail_fast: bool,
+        reply_handle: ReplyHandle<FragmentsProcessingSummary>,
+    },
+    RemoveTransactions(Vec<FragmentId>, FragmentStatus),
+    /// Re-validate pool entries touched by the ledger state change from
+    /// applying a new block.
+    MaintainPool {
+        ledger: LedgerRef,
+        ledger_params: LedgerParameters,
+        /// The block whose application triggered this pass, used to avoid
+        /// re-validating a fragment twice against the same tip.
+        block: HeaderId,
+        touched_fragment_ids: Vec<FragmentId>,
+    },
+    /// Subscribe to the pool's fragment lifecycle event stream.
+    Subscribe(ReplyHandle<broadcast::Receiver<FragmentEvent>>),
+    GetLatencyStats(ReplyHandle<FragmentLatencyStats>),
+    GetLogs(ReplyHandle<Vec<Log>>),
+    GetStatuses(Vec<FragmentId>, ReplyHandle<HashMap<FragmentId, FragmentStatus>>),
+    SelectTransactions {
+        pool_idx: usize,
+        ledger: LedgerRef,
+        ledger_params: LedgerParameters,
+        selection_alg: SelectionAlg,
+        reply_handle: ReplyHandle<Contents>,
+        soft_deadline_future: DeadlineFuture,
+        hard_deadline_future: DeadlineFuture,
+    },
+}
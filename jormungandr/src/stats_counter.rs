@@ -0,0 +1,109 @@
+use std::sync::{Arc, Mutex};
+
+use hdrhistogram::Histogram;
+
+/// Percentile summary computed on read from an underlying latency
+/// histogram, in microseconds.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyPercentiles {
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FragmentLatencyStats {
+    pub receive_to_accept: LatencyPercentiles,
+    pub accept_to_in_block: LatencyPercentiles,
+}
+
+fn percentiles_of(histogram: &Histogram<u64>) -> LatencyPercentiles {
+    LatencyPercentiles {
+        p50_us: histogram.value_at_quantile(0.50),
+        p90_us: histogram.value_at_quantile(0.90),
+        p99_us: histogram.value_at_quantile(0.99),
+        max_us: histogram.max(),
+        count: histogram.len(),
+    }
+}
+
+struct Inner {
+    tx_recv_cnt: usize,
+    receive_to_accept: Histogram<u64>,
+    accept_to_in_block: Histogram<u64>,
+}
+
+#[derive(Clone)]
+pub struct StatsCounter {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Default for StatsCounter {
+    fn default() -> Self {
+        StatsCounter {
+            inner: Arc::new(Mutex::new(Inner {
+                tx_recv_cnt: 0,
+                // 3 significant digits is enough resolution for
+                // microsecond-scale latencies; auto-resizing so an
+                // unexpectedly long stall doesn't just get clamped away.
+                receive_to_accept: Histogram::new(3).expect("valid histogram parameters"),
+                accept_to_in_block: Histogram::new(3).expect("valid histogram parameters"),
+            })),
+        }
+    }
+}
+
+impl StatsCounter {
+    pub fn add_tx_recv_cnt(&self, count: usize) {
+        self.inner.lock().unwrap().tx_recv_cnt += count;
+    }
+
+    pub fn tx_recv_cnt(&self) -> usize {
+        self.inner.lock().unwrap().tx_recv_cnt
+    }
+
+    pub fn record_receive_to_accept_latency(&self, micros: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let _ = inner.receive_to_accept.record(micros);
+    }
+
+    pub fn record_accept_to_in_block_latency(&self, micros: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let _ = inner.accept_to_in_block.record(micros);
+    }
+
+    /// Percentiles over the samples seen since the last read; the
+    /// underlying histograms reset on read so values reflect recent
+    /// behavior rather than an unbounded all-time aggregate.
+    pub fn fragment_latency_stats(&self) -> FragmentLatencyStats {
+        let mut inner = self.inner.lock().unwrap();
+        let stats = FragmentLatencyStats {
+            receive_to_accept: percentiles_of(&inner.receive_to_accept),
+            accept_to_in_block: percentiles_of(&inner.accept_to_in_block),
+        };
+        inner.receive_to_accept.reset();
+        inner.accept_to_in_block.reset();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragment_latency_stats_reflects_recorded_samples_and_resets_on_read() {
+        let stats_counter = StatsCounter::default();
+        stats_counter.record_receive_to_accept_latency(100);
+        stats_counter.record_receive_to_accept_latency(300);
+
+        let stats = stats_counter.fragment_latency_stats();
+        assert_eq!(stats.receive_to_accept.count, 2);
+        assert_eq!(stats.receive_to_accept.max_us, 300);
+
+        let stats_after_reset = stats_counter.fragment_latency_stats();
+        assert_eq!(stats_after_reset.receive_to_accept.count, 0);
+    }
+}
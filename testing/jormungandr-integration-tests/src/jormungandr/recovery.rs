@@ -190,3 +190,71 @@ pub fn test_node_recovers_kill_signal() {
         snapshot_before, snapshot_after
     );
 }
+
+#[test]
+pub fn test_node_recovers_pending_fragment_from_persistent_log() {
+    let temp_dir = TempDir::new().unwrap();
+    let jcli: JCli = Default::default();
+
+    let sender = startup::create_new_utxo_address();
+    let account_receiver = startup::create_new_account_address();
+    let utxo_receiver = startup::create_new_utxo_address();
+
+    let config = ConfigurationBuilder::new()
+        .with_funds(vec![InitialUTxO {
+            address: sender.address(),
+            value: 100.into(),
+        }])
+        .with_storage(&temp_dir.child("storage"))
+        // Replay on startup only runs when a persistent log directory is
+        // configured, which is exactly the path this test means to exercise.
+        .with_persistent_log(&temp_dir.child("persistent_log"))
+        .build(&temp_dir);
+
+    let jormungandr = Starter::new().config(config.clone()).start().unwrap();
+    let utxo_sender = config.block0_utxo_for_address(&sender);
+
+    const TX_VALUE: u64 = 50;
+    let mut tx = jcli.transaction_builder(jormungandr.genesis_block_hash());
+    let transaction_message = tx
+        .new_transaction()
+        .add_input_from_utxo(&utxo_sender)
+        .add_output(&utxo_receiver.address().to_string(), TX_VALUE.into())
+        .finalize()
+        .seal_with_witness_for_address(&sender)
+        .to_message();
+    let tx_id = tx.fragment_id();
+    let expected_utxo = UTxOInfo::new(tx_id, 0, utxo_receiver.address(), TX_VALUE.into());
+
+    // Submit the fragment and kill the node right away, before a block has
+    // had a chance to include it: at this point it is only durable via the
+    // persistent fragment log, not via the ledger state captured in
+    // storage, so this exercises the replay-on-startup path rather than
+    // the storage-backed recovery already covered above.
+    jcli.fragment_sender(&jormungandr).send(&transaction_message);
+    jormungandr.stop();
+
+    let jormungandr = Starter::new()
+        .temp_dir(temp_dir)
+        .config(config)
+        .role(Role::Leader)
+        .start()
+        .unwrap();
+
+    jormungandr
+        .rest()
+        .raw()
+        .send_until_ok(
+            |raw| raw.account_state(&account_receiver),
+            Default::default(),
+        )
+        .expect("timeout occured when pooling address endpoint");
+
+    // The fragment was replayed from the persistent log on startup, so it
+    // still makes it into a block even though it was never acknowledged
+    // before the restart.
+    jcli.rest()
+        .v0()
+        .utxo()
+        .assert_contains(&expected_utxo, &jormungandr.rest_uri());
+}